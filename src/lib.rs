@@ -21,15 +21,30 @@
 extern crate err_derive;
 #[macro_use]
 extern crate smart_default;
+#[macro_use]
+extern crate serde_derive;
+
+mod profiles;
+mod watch;
+
+pub use crate::{profiles::PStateProfiles, watch::PowerSourceWatcher};
 
 use std::{
     fmt::Display,
     fs::{File, OpenOptions},
-    io::{self, Read, Write},
+    io::{self, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
     str::FromStr,
 };
 
+/// MSR_IA32_MISC_ENABLE, which the firmware may use to force turbo off
+/// regardless of what the `no_turbo` sysfs file reports.
+const MSR_IA32_MISC_ENABLE: u64 = 0x1A0;
+
+/// Bit 38 of `MSR_IA32_MISC_ENABLE`; when set, turbo is disabled at the
+/// firmware level and writes to `no_turbo` will not bring it back.
+const MISC_ENABLE_TURBO_DISABLE: u64 = 1 << 38;
+
 #[derive(Debug, Error)]
 pub enum PStateError {
     #[error(display = "failed to get min perf pstate value: {}", _0)]
@@ -38,17 +53,32 @@ pub enum PStateError {
     GetMaxPerf(io::Error),
     #[error(display = "failed to get no turbo pstate value: {}", _0)]
     GetNoTurbo(io::Error),
+    #[error(display = "failed to read MSR_IA32_MISC_ENABLE: {}", _0)]
+    GetMsr(io::Error),
     #[error(display = "intel_pstate directory not found")]
     NotFound,
+    #[error(display = "/dev/cpu/*/msr not found: is the msr kernel module loaded?")]
+    MsrNotFound,
     #[error(display = "failed to set min perf pstate value to {}: {}", _0, _1)]
     SetMinPerf(u8, io::Error),
     #[error(display = "failed to set max perf pstate value to {}: {}", _0, _1)]
     SetMaxPerf(u8, io::Error),
     #[error(display = "failed to set no turbo pstate value to {}: {}", _0, _1)]
     SetNoTurbo(bool, io::Error),
+    #[error(
+        display = "requested no_turbo = {}, but firmware keeps turbo locked disabled",
+        _0
+    )]
+    TurboLockedByFirmware(bool),
+    #[error(display = "no pstate profile named {:?}", _0)]
+    ProfileNotFound(String),
+    #[error(display = "failed to get num_pstates value: {}", _0)]
+    GetNumPstates(io::Error),
+    #[error(display = "failed to get turbo_pct value: {}", _0)]
+    GetTurboPct(io::Error),
 }
 
-#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, SmartDefault)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, SmartDefault, Serialize, Deserialize)]
 /// A set of pstate values that was retrieved, or is to be set.
 pub struct PStateValues {
     pub min_perf_pct: u8,
@@ -63,6 +93,19 @@ impl PStateValues {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+/// The relationship between the non-turbo and turbo portions of the
+/// P-state range, so that a `max_perf_pct`/`min_perf_pct` value can be
+/// translated into whether it is actually achievable without turbo.
+pub struct PStateInfo {
+    /// The highest percentage reachable without relying on turbo.
+    pub base_max_pct: u8,
+    /// The highest percentage reachable at all; always `100`.
+    pub turbo_max_pct: u8,
+    /// Whether turbo is currently available to reach `turbo_max_pct`.
+    pub turbo_available: bool,
+}
+
 /// Handle for fetching and modifying Intel PState kernel parameters.
 ///
 /// # Note
@@ -70,7 +113,7 @@ impl PStateValues {
 /// - Currently, ony Linux is supported.
 /// - Setting parameters will require root permissions.
 pub struct PState {
-    path: PathBuf,
+    pub(crate) path: PathBuf,
 }
 
 impl PState {
@@ -114,9 +157,110 @@ impl PState {
     }
 
     /// Set the no_turbo value; `true` will disable turbo.
+    ///
+    /// If the caller asks to enable turbo (`no_turbo = false`) but the
+    /// firmware has force-disabled it (see [`PState::turbo_disabled`]),
+    /// the sysfs write will silently have no effect. This is detected by
+    /// reading back the applied state, and reported as
+    /// [`PStateError::TurboLockedByFirmware`] instead of succeeding
+    /// silently. When `/dev/cpu/0/msr` is missing (e.g. the `msr` module
+    /// isn't loaded) the firmware check is skipped and only the sysfs
+    /// `no_turbo` read-back decides; any other MSR read error is returned.
     pub fn set_no_turbo(&self, no_turbo: bool) -> Result<(), PStateError> {
         write_file(self.path.join("no_turbo"), if no_turbo { "1" } else { "0" })
-            .map_err(|why| PStateError::SetNoTurbo(no_turbo, why))
+            .map_err(|why| PStateError::SetNoTurbo(no_turbo, why))?;
+
+        if !no_turbo {
+            match self.turbo_disabled() {
+                Ok(true) => return Err(PStateError::TurboLockedByFirmware(no_turbo)),
+                Ok(false) | Err(PStateError::MsrNotFound) => {}
+                Err(why) => return Err(why),
+            }
+
+            if self.no_turbo()? {
+                return Err(PStateError::TurboLockedByFirmware(no_turbo));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the firmware has force-disabled turbo, independent of what
+    /// the `no_turbo` sysfs file reports.
+    ///
+    /// Some BIOSes flip `MSR_IA32_MISC_ENABLE_TURBO_DISABLE` when switching
+    /// between AC and battery, in which case `no_turbo` can read `0` while
+    /// turbo is actually unavailable and writes to it silently have no
+    /// effect. This checks the MSR directly, and also treats turbo as
+    /// disabled when the platform reports no turbo-only P-states at all.
+    pub fn turbo_disabled(&self) -> Result<bool, PStateError> {
+        if read_msr(0, MSR_IA32_MISC_ENABLE)? & MISC_ENABLE_TURBO_DISABLE != 0 {
+            return Ok(true);
+        }
+
+        // If the turbo-only portion of the P-state range is empty, the max
+        // non-turbo P-state is the same as the max turbo P-state.
+        if let Ok(turbo_pct) = self.turbo_pct() {
+            if turbo_pct == 0 {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// `true` if turbo is unavailable, either because `no_turbo` is set or
+    /// because the firmware has force-disabled it underneath sysfs.
+    ///
+    /// Falls back to the sysfs-only reading when `/dev/cpu/0/msr` is missing
+    /// (e.g. the `msr` module isn't loaded); any other MSR read error is
+    /// returned.
+    pub fn effective_no_turbo(&self) -> Result<bool, PStateError> {
+        if self.no_turbo()? {
+            return Ok(true);
+        }
+
+        match self.turbo_disabled() {
+            Ok(value) => Ok(value),
+            Err(PStateError::MsrNotFound) => Ok(false),
+            Err(why) => Err(why),
+        }
+    }
+
+    /// The percentage of the P-state range that is turbo-only.
+    fn turbo_pct(&self) -> Result<u8, PStateError> {
+        parse_file(self.path.join("turbo_pct")).map_err(|why| PStateError::GetTurboPct(why))
+    }
+
+    /// The number of P-states the platform exposes, spanning both the
+    /// non-turbo and turbo-only portions of the range.
+    pub fn num_pstates(&self) -> Result<u32, PStateError> {
+        parse_file(self.path.join("num_pstates")).map_err(|why| PStateError::GetNumPstates(why))
+    }
+
+    /// The turbo vs non-turbo headroom in the P-state range, so that a
+    /// requested `max_perf_pct` can be interpreted in terms of what it
+    /// actually buys: `base_max_pct..turbo_max_pct` is only reachable when
+    /// `turbo_available` is `true`.
+    ///
+    /// `base_max_pct` is derived from `num_pstates` and `turbo_pct` (the
+    /// count of turbo-only P-states over the total), rather than read as a
+    /// frequency ratio directly, since sysfs exposes no
+    /// `max_pstate_physical`-equivalent file. `turbo_available` is the
+    /// negation of [`PState::effective_no_turbo`], so it falls back to the
+    /// sysfs `no_turbo` reading when `/dev/cpu/0/msr` is missing.
+    pub fn info(&self) -> Result<PStateInfo, PStateError> {
+        let num_pstates = self.num_pstates()?;
+        let turbo_pct = u32::from(self.turbo_pct()?);
+
+        let turbo_available = !self.effective_no_turbo()?;
+
+        let turbo_pstates = num_pstates.saturating_mul(turbo_pct) / 100;
+        let base_pstates = num_pstates.saturating_sub(turbo_pstates);
+        let base_max_pct =
+            if num_pstates == 0 { 100 } else { (base_pstates * 100 / num_pstates) as u8 };
+
+        Ok(PStateInfo { base_max_pct, turbo_max_pct: 100, turbo_available })
     }
 
     pub fn values(&self) -> Result<PStateValues, PStateError> {
@@ -137,6 +281,25 @@ impl PState {
     }
 }
 
+/// Read an 8-byte MSR value at `offset` for the given CPU, via
+/// `/dev/cpu/{cpu}/msr`.
+fn read_msr(cpu: u32, offset: u64) -> Result<u64, PStateError> {
+    let mut file = File::open(format!("/dev/cpu/{}/msr", cpu)).map_err(|why| {
+        if why.kind() == io::ErrorKind::NotFound {
+            PStateError::MsrNotFound
+        } else {
+            PStateError::GetMsr(why)
+        }
+    })?;
+
+    file.seek(SeekFrom::Start(offset)).map_err(PStateError::GetMsr)?;
+
+    let mut bytes = [0u8; 8];
+    file.read_exact(&mut bytes).map_err(PStateError::GetMsr)?;
+
+    Ok(u64::from_le_bytes(bytes))
+}
+
 fn read_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
     let mut data = String::new();
 
@@ -157,7 +320,7 @@ fn write_file<P: AsRef<Path>, S: AsRef<[u8]>>(path: P, data: S) -> io::Result<()
     Ok(())
 }
 
-fn parse_file<F: FromStr, P: AsRef<Path>>(path: P) -> io::Result<F>
+pub(crate) fn parse_file<F: FromStr, P: AsRef<Path>>(path: P) -> io::Result<F>
 where
     F::Err: Display,
 {
@@ -0,0 +1,53 @@
+use crate::{PState, PStateError, PStateValues};
+use std::collections::HashMap;
+
+/// A named collection of [`PStateValues`], applied through a [`PState`]
+/// handle.
+///
+/// The kernel has historically leaked one governor's min/max percentages
+/// into another because sysfs overrides were tracked in a single global
+/// slot. `PStateProfiles` keeps each context's limits in its own named
+/// slot (e.g. `"ac"`, `"battery"`, `"performance"`) so a userspace manager
+/// can snapshot the current state, switch contexts freely, and reapply the
+/// right limits on power-source or profile changes. The whole set derives
+/// `Serialize`/`Deserialize` so it can be persisted across suspend/resume
+/// and reboots.
+///
+/// [`Self::apply`] writes `min_perf_pct`, `max_perf_pct`, and `no_turbo`
+/// sequentially (see [`PState::set_values`]), not atomically: an error
+/// partway through leaves the preceding writes in place.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PStateProfiles {
+    profiles: HashMap<String, PStateValues>,
+}
+
+impl PStateProfiles {
+    /// Create an empty set of profiles.
+    pub fn new() -> Self { Self::default() }
+
+    /// Store `values` under `name`, replacing any previous profile of the
+    /// same name.
+    pub fn set(&mut self, name: impl Into<String>, values: PStateValues) {
+        self.profiles.insert(name.into(), values);
+    }
+
+    /// Capture the `PState` handle's current values into the named slot.
+    pub fn snapshot(&mut self, name: impl Into<String>, pstate: &PState) -> Result<(), PStateError> {
+        let values = pstate.values()?;
+        self.set(name, values);
+        Ok(())
+    }
+
+    /// Fetch the stored values for `name`, if any.
+    pub fn get(&self, name: &str) -> Option<PStateValues> { self.profiles.get(name).copied() }
+
+    /// Remove the named profile, if it exists.
+    pub fn remove(&mut self, name: &str) -> Option<PStateValues> { self.profiles.remove(name) }
+
+    /// Apply the named profile's values to the given `PState` handle.
+    pub fn apply(&self, name: &str, pstate: &PState) -> Result<(), PStateError> {
+        let values =
+            self.get(name).ok_or_else(|| PStateError::ProfileNotFound(name.to_string()))?;
+        pstate.set_values(values)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{parse_file, PState, PStateError, PStateProfiles, PStateValues};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// How often to poll `/sys/class/power_supply/*/online` for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A running power-source watcher, as returned by
+/// [`PState::watch_power_source`].
+///
+/// Dropping this handle does not stop the watcher; call [`Self::stop`] to
+/// end the poll loop and join its thread.
+pub struct PowerSourceWatcher {
+    stop:   Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PowerSourceWatcher {
+    /// Signal the watcher thread to exit, and wait for it to do so.
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl PState {
+    /// Watch `/sys/class/power_supply/*/online` for AC/battery transitions,
+    /// and apply the matching `"ac"` or `"battery"` profile from `profiles`
+    /// through this `PState` whenever one occurs.
+    ///
+    /// BIOSes are known to flip the firmware turbo-disable bit when
+    /// switching between AC and battery, so this turns the crate from a
+    /// one-shot setter into a small daemon-capable control loop that reacts
+    /// to exactly the events that mutate that state. `callback` is invoked
+    /// after each transition for which `profiles` holds the matching
+    /// profile, with that profile's values and any error encountered while
+    /// applying it; a transition with no matching profile applies nothing
+    /// and does not invoke `callback`.
+    pub fn watch_power_source<F>(
+        &self,
+        profiles: PStateProfiles,
+        mut callback: F,
+    ) -> PowerSourceWatcher
+    where
+        F: FnMut(PStateValues, Option<PStateError>) + Send + 'static,
+    {
+        let pstate = PState { path: self.path.clone() };
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let thread = thread::spawn(move || {
+            let mut online = read_ac_online();
+
+            while !thread_stop.load(Ordering::SeqCst) {
+                thread::sleep(POLL_INTERVAL);
+
+                let now_online = read_ac_online();
+                if now_online == online {
+                    continue;
+                }
+
+                online = now_online;
+                let name = if online.unwrap_or(true) { "ac" } else { "battery" };
+
+                if let Some(values) = profiles.get(name) {
+                    let result = profiles.apply(name, &pstate);
+                    callback(values, result.err());
+                }
+            }
+        });
+
+        PowerSourceWatcher { stop, thread: Some(thread) }
+    }
+}
+
+/// Read the `online` state of the first power supply that exposes one
+/// (the AC/mains adapter). Returns `None` if no such supply is found.
+fn read_ac_online() -> Option<bool> {
+    let entries = fs::read_dir("/sys/class/power_supply").ok()?;
+
+    for entry in entries.filter_map(Result::ok) {
+        let online_path: PathBuf = entry.path().join("online");
+        if let Ok(value) = parse_file::<u8, _>(&online_path) {
+            return Some(value > 0);
+        }
+    }
+
+    None
+}